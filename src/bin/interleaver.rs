@@ -0,0 +1,19 @@
+//! Test fixture: writes alternating lines to stdout and stderr with a
+//! small delay between each so a naive "drain stdout, then stderr"
+//! capture would reorder them, but a concurrent capture would not.
+
+use std::io::Write;
+use std::time::Duration;
+
+fn main() {
+    for i in 0..6 {
+        if i % 2 == 0 {
+            println!("out-{}", i);
+            std::io::stdout().flush().unwrap();
+        } else {
+            eprintln!("err-{}", i);
+            std::io::stderr().flush().unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
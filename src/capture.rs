@@ -0,0 +1,149 @@
+//! Concurrent stdout/stderr capture.
+//!
+//! Draining stdout to completion before even starting on stderr deadlocks
+//! as soon as either pipe's OS buffer fills (~64KB): the child blocks
+//! writing to the pipe we haven't gotten to yet, while we sit blocked
+//! reading the one we have. It also destroys the real interleaving of the
+//! two streams, since all of stderr ends up appended after all of stdout
+//! in the HTML regardless of when it was actually written. We read both
+//! pipes concurrently from their own threads, timestamp each line as it
+//! arrives onto a shared buffer, and merge by arrival order once the
+//! child exits.
+
+use crate::timeout;
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug)]
+struct Line {
+    arrived: Instant,
+    bytes: Vec<u8>,
+}
+
+/// Result of a captured run: the merged output, the child's exit code,
+/// and whether `--timeout` had to kill it before it exited on its own.
+pub struct CaptureOutcome {
+    pub output_text: String,
+    pub exit_code: i32,
+    pub timed_out: bool,
+}
+
+/// Run `command`, capturing stdout and stderr concurrently so neither
+/// stream can starve the other, and returns them merged in the order
+/// their lines actually arrived. If `timeout` elapses before the command
+/// exits, it is killed and the output captured so far is still returned.
+pub fn run(command: &[String], timeout: Option<Duration>) -> Result<CaptureOutcome> {
+    let program = &command[0];
+    let args = &command[1..];
+
+    let mut child = Command::new(program)
+        .args(args)
+        .env("CLICOLOR_FORCE", "1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("Failed to execute command: {}", program))?;
+
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    let watcher = timeout.map(|deadline| {
+        timeout::spawn_watcher(
+            child.id(),
+            deadline,
+            Arc::clone(&timed_out),
+            Arc::clone(&finished),
+        )
+    });
+
+    let lines: Arc<Mutex<Vec<Line>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let stdout = child.stdout.take().expect("stdout was piped");
+    let stderr = child.stderr.take().expect("stderr was piped");
+
+    let stdout_handle = spawn_reader(stdout, Stream::Stdout, Arc::clone(&lines));
+    let stderr_handle = spawn_reader(stderr, Stream::Stderr, Arc::clone(&lines));
+
+    stdout_handle
+        .join()
+        .expect("stdout reader thread panicked")?;
+    stderr_handle
+        .join()
+        .expect("stderr reader thread panicked")?;
+
+    // Flip this before wait() reaps the child: once it's reaped, the pid
+    // is free for the OS to reuse, and the watcher thread must not be
+    // able to send a late signal to whatever ends up with that pid.
+    finished.store(true, Ordering::SeqCst);
+    let status = child.wait().context("Failed to wait for command")?;
+    if let Some(watcher) = watcher {
+        let _ = watcher.join();
+    }
+
+    let timed_out = timed_out.load(Ordering::SeqCst);
+    let exit_code = status.code().unwrap_or(1);
+
+    let mut lines = Arc::try_unwrap(lines)
+        .expect("reader threads have exited")
+        .into_inner()
+        .expect("lines mutex poisoned");
+    lines.sort_by_key(|line| line.arrived);
+
+    let mut output_buffer = Vec::new();
+    for line in &lines {
+        output_buffer.extend_from_slice(&line.bytes);
+        output_buffer.push(b'\n');
+    }
+
+    let output_text = String::from_utf8_lossy(&output_buffer).to_string();
+
+    Ok(CaptureOutcome {
+        output_text,
+        exit_code,
+        timed_out,
+    })
+}
+
+fn spawn_reader<R>(reader: R, stream: Stream, lines: Arc<Mutex<Vec<Line>>>) -> JoinHandle<Result<()>>
+where
+    R: Read + Send + 'static,
+{
+    std::thread::spawn(move || -> Result<()> {
+        let reader = BufReader::new(reader);
+        for line in reader.split(b'\n') {
+            let line = line.context("Failed to read command output")?;
+            let arrived = Instant::now();
+
+            match stream {
+                Stream::Stdout => {
+                    let mut stdout = std::io::stdout();
+                    stdout.write_all(&line)?;
+                    stdout.write_all(b"\n")?;
+                    stdout.flush()?;
+                }
+                Stream::Stderr => {
+                    let mut stderr = std::io::stderr();
+                    stderr.write_all(&line)?;
+                    stderr.write_all(b"\n")?;
+                    stderr.flush()?;
+                }
+            }
+
+            lines
+                .lock()
+                .expect("lines mutex poisoned")
+                .push(Line { arrived, bytes: line });
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,407 @@
+//! A minimal terminal grid emulator.
+//!
+//! Progress bars and spinners (cargo, npm, docker, pip, ...) are drawn with
+//! `\r`, cursor-up, and line-erase sequences that overwrite the same
+//! screen region many times. Feeding that raw stream straight into
+//! `ansi_to_html::convert` bakes every intermediate redraw into the HTML
+//! instead of just the final frame the user actually saw. `Emulator`
+//! replays the stream onto a 2D cell grid the way a real terminal would,
+//! then serializes the settled grid back into a flat ANSI string for
+//! `ansi_to_html` to convert as usual.
+
+#[derive(Clone, Default)]
+struct Cell {
+    ch: char,
+    style: String,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Cell {
+            ch: ' ',
+            style: String::new(),
+        }
+    }
+}
+
+pub struct Emulator {
+    grid: Vec<Vec<Cell>>,
+    cursor_row: usize,
+    cursor_col: usize,
+    style: String,
+    cols: usize,
+    rows: usize,
+}
+
+impl Emulator {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Emulator {
+            grid: vec![vec![Cell::blank(); cols]],
+            cursor_row: 0,
+            cursor_col: 0,
+            style: String::new(),
+            cols,
+            rows,
+        }
+    }
+
+    pub fn feed(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => self.consume_escape(&mut chars),
+                '\r' => self.cursor_col = 0,
+                '\n' => self.newline(),
+                _ => self.put_char(c),
+            }
+        }
+    }
+
+    /// Consume one escape sequence following an ESC byte already taken
+    /// from `chars`. CSI (`ESC [`) is dispatched to [`Self::apply_csi`];
+    /// everything else is discarded wholesale rather than just dropping
+    /// the lone ESC byte, since otherwise the rest of the sequence (an
+    /// OSC title/hyperlink payload, a charset designation, ...) falls
+    /// through and gets printed into the grid as literal text.
+    fn consume_escape<I: Iterator<Item = char>>(&mut self, chars: &mut std::iter::Peekable<I>) {
+        match chars.peek() {
+            Some('[') => {
+                chars.next();
+                let mut seq = String::new();
+                while let Some(&next) = chars.peek() {
+                    seq.push(next);
+                    chars.next();
+                    if next.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+                self.apply_csi(&seq);
+            }
+            Some(']') => {
+                chars.next();
+                // OSC (window title, hyperlinks, ...): consume up to its
+                // terminator, either BEL or the two-byte ST (`ESC \`).
+                loop {
+                    match chars.next() {
+                        None | Some('\x07') => break,
+                        Some('\x1b') if chars.peek() == Some(&'\\') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(_) => continue,
+                    }
+                }
+            }
+            Some('(') | Some(')') | Some('#') | Some('%') => {
+                // Two-byte forms, e.g. charset designation (`ESC ( B`).
+                chars.next();
+                chars.next();
+            }
+            Some(_) => {
+                // One-byte forms (`ESC 7`, `ESC 8`, `ESC M`, `ESC =`, ...).
+                chars.next();
+            }
+            None => {}
+        }
+    }
+
+    /// Serialize the settled grid back into an ANSI string, emitting an
+    /// SGR run each time the style changes.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut active_style = String::new();
+
+        for row in &self.grid {
+            let mut line = String::new();
+            for cell in row {
+                if cell.style != active_style {
+                    // Always reset before applying the next style, even
+                    // when both are non-empty: styles only ever accumulate
+                    // in `apply_sgr`, so without a reset here attributes
+                    // from the previous run (e.g. bold) would bleed into a
+                    // run that never asked for them.
+                    line.push_str("\x1b[0m");
+                    if !cell.style.is_empty() {
+                        line.push_str(&format!("\x1b[{}m", cell.style));
+                    }
+                    active_style = cell.style.clone();
+                }
+                line.push(cell.ch);
+            }
+            out.push_str(line.trim_end_matches(' '));
+            out.push('\n');
+        }
+
+        if !active_style.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+
+        out
+    }
+
+    fn apply_csi(&mut self, seq: &str) {
+        let Some(cmd) = seq.chars().last() else {
+            return;
+        };
+        let params_str = &seq[..seq.len() - cmd.len_utf8()];
+        let params: Vec<i64> = if params_str.is_empty() {
+            Vec::new()
+        } else {
+            params_str.split(';').map(|p| p.parse().unwrap_or(0)).collect()
+        };
+        // Absent parameters take the op's default; an explicitly-passed `0`
+        // is returned as-is and is not promoted to the default here.
+        let param = |idx: usize, default: i64| -> i64 {
+            match params.get(idx) {
+                None => default,
+                Some(&v) => v,
+            }
+        };
+        // Relative cursor movement treats 0 the same as an absent
+        // parameter (both move by 1, so `ESC[0C` acts like `ESC[C`); this is a
+        // property of these specific ops, not of CSI params in general,
+        // so it's applied here rather than inside `param` itself.
+        let movement = |idx: usize| -> i64 { param(idx, 1).max(1) };
+
+        match cmd {
+            'A' => self.cursor_row = self.cursor_row.saturating_sub(movement(0) as usize),
+            'B' => {
+                self.cursor_row = (self.cursor_row + movement(0) as usize).min(self.rows - 1);
+                self.ensure_row(self.cursor_row);
+            }
+            'C' => self.cursor_col = (self.cursor_col + movement(0) as usize).min(self.cols - 1),
+            'D' => self.cursor_col = self.cursor_col.saturating_sub(movement(0) as usize),
+            'H' | 'f' => {
+                let row = ((param(0, 1) - 1).max(0) as usize).min(self.rows - 1);
+                let col = (param(1, 1) - 1).max(0) as usize;
+                self.ensure_row(row);
+                self.cursor_row = row;
+                self.cursor_col = col.min(self.cols - 1);
+            }
+            'K' => self.erase_in_line(params.first().copied().unwrap_or(0)),
+            'J' => self.erase_in_screen(params.first().copied().unwrap_or(0)),
+            'm' => self.apply_sgr(&params),
+            _ => {}
+        }
+    }
+
+    fn erase_in_line(&mut self, mode: i64) {
+        self.ensure_row(self.cursor_row);
+        let row = &mut self.grid[self.cursor_row];
+        let cursor_col = self.cursor_col.min(row.len() - 1);
+        match mode {
+            0 => row[cursor_col..].fill(Cell::blank()),
+            1 => row[..=cursor_col].fill(Cell::blank()),
+            2 => row.fill(Cell::blank()),
+            _ => {}
+        }
+    }
+
+    fn erase_in_screen(&mut self, mode: i64) {
+        match mode {
+            2 | 3 => {
+                self.grid = vec![vec![Cell::blank(); self.cols]];
+                self.cursor_row = 0;
+                self.cursor_col = 0;
+            }
+            0 => {
+                self.erase_in_line(0);
+                for row in self.grid.iter_mut().skip(self.cursor_row + 1) {
+                    row.fill(Cell::blank());
+                }
+            }
+            1 => {
+                self.erase_in_line(1);
+                for row in self.grid.iter_mut().take(self.cursor_row) {
+                    row.fill(Cell::blank());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_sgr(&mut self, params: &[i64]) {
+        if params.is_empty() {
+            self.style.clear();
+            return;
+        }
+
+        // Walk params sequentially rather than filtering zeros out of a
+        // flat list: `38;5;0` (256-color black fg) and `38;2;0;0;0`
+        // (truecolor black) are multi-value units whose trailing zeros
+        // are color components, not resets, and must stay attached to
+        // the `38`/`48` that introduced them.
+        let mut codes: Vec<String> = Vec::new();
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => {
+                    self.style.clear();
+                    codes.clear();
+                    i += 1;
+                }
+                p @ (38 | 48) if params.get(i + 1) == Some(&5) && params.len() > i + 2 => {
+                    codes.push(format!("{};5;{}", p, params[i + 2]));
+                    i += 3;
+                }
+                p @ (38 | 48) if params.get(i + 1) == Some(&2) && params.len() > i + 4 => {
+                    codes.push(format!(
+                        "{};2;{};{};{}",
+                        p,
+                        params[i + 2],
+                        params[i + 3],
+                        params[i + 4]
+                    ));
+                    i += 5;
+                }
+                p => {
+                    codes.push(p.to_string());
+                    i += 1;
+                }
+            }
+        }
+
+        if codes.is_empty() {
+            return;
+        }
+        if self.style.is_empty() {
+            self.style = codes.join(";");
+        } else {
+            self.style.push(';');
+            self.style.push_str(&codes.join(";"));
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        if self.cursor_col >= self.cols {
+            self.newline();
+        }
+        self.ensure_row(self.cursor_row);
+        self.grid[self.cursor_row][self.cursor_col] = Cell {
+            ch: c,
+            style: self.style.clone(),
+        };
+        self.cursor_col += 1;
+    }
+
+    /// Advance to the next row, scrolling the grid up (dropping its oldest
+    /// row) once the cursor is already on the bottom row rather than
+    /// growing the grid without bound.
+    fn newline(&mut self) {
+        self.cursor_col = 0;
+        if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+            self.ensure_row(self.cursor_row);
+        } else {
+            if self.grid.len() < self.rows {
+                self.grid.push(vec![Cell::blank(); self.cols]);
+            } else {
+                self.grid.remove(0);
+                self.grid.push(vec![Cell::blank(); self.cols]);
+            }
+            self.cursor_row = self.grid.len() - 1;
+        }
+    }
+
+    fn ensure_row(&mut self, row: usize) {
+        let row = row.min(self.rows - 1);
+        while self.grid.len() <= row {
+            self.grid.push(vec![Cell::blank(); self.cols]);
+        }
+    }
+}
+
+/// Replay `input` through the grid emulator at the given width/height and
+/// return the settled screen as an ANSI string. `\n` past the bottom row
+/// scrolls the grid rather than growing it past `rows`.
+pub fn emulate(input: &str, cols: usize, rows: usize) -> String {
+    let mut emulator = Emulator::new(cols, rows);
+    emulator.feed(input);
+    emulator.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carriage_return_overwrites_the_current_line() {
+        let out = emulate("foobar\rbaz", 10, 24);
+        assert_eq!(out.trim_end_matches('\n'), "bazbar");
+    }
+
+    #[test]
+    fn cursor_up_redraws_a_previous_line() {
+        // Write two lines, move up one, return to column 0, and overwrite it.
+        let out = emulate("first\nsecond\x1b[1A\rreplaced", 10, 24);
+        let lines: Vec<&str> = out.trim_end_matches('\n').split('\n').collect();
+        assert_eq!(lines, vec!["replaced", "second"]);
+    }
+
+    #[test]
+    fn erase_in_line_clears_from_cursor_to_end() {
+        let out = emulate("hello world\r\x1b[5C\x1b[K", 20, 24);
+        assert_eq!(out.trim_end_matches('\n'), "hello");
+    }
+
+    #[test]
+    fn sgr_256_color_is_kept_as_one_unit() {
+        let out = emulate("\x1b[38;5;0mx", 10, 24);
+        assert!(out.contains("\x1b[38;5;0m"));
+    }
+
+    #[test]
+    fn sgr_truecolor_is_kept_as_one_unit() {
+        let out = emulate("\x1b[38;2;0;0;0mx", 10, 24);
+        assert!(out.contains("\x1b[38;2;0;0;0m"));
+    }
+
+    #[test]
+    fn newline_past_the_bottom_row_scrolls_instead_of_growing() {
+        let out = emulate("a\nb\nc\nd", 10, 2);
+        let lines: Vec<&str> = out.trim_end_matches('\n').split('\n').collect();
+        assert_eq!(lines, vec!["c", "d"]);
+    }
+
+    #[test]
+    fn explicit_zero_column_addresses_the_first_column() {
+        let out = emulate("\x1b[5;0Hx", 10, 24);
+        let lines: Vec<&str> = out.trim_end_matches('\n').split('\n').collect();
+        assert_eq!(lines[4], "x");
+    }
+
+    #[test]
+    fn absolute_position_past_the_bottom_row_clamps_to_it() {
+        let out = emulate("\x1b[50;1Hlast", 10, 3);
+        let lines: Vec<&str> = out.trim_end_matches('\n').split('\n').collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2], "last");
+    }
+
+    #[test]
+    fn reset_is_emitted_between_two_non_empty_styles() {
+        let out = emulate("\x1b[1;31mA\x1b[0m\x1b[32mB", 10, 24);
+        // Without an explicit reset, "B" would inherit bold from "A"'s run.
+        assert!(out.contains("A\x1b[0m\x1b[32mB"), "got: {:?}", out);
+    }
+
+    #[test]
+    fn osc_title_sequence_is_consumed_not_printed() {
+        let out = emulate("before\x1b]0;MYTITLE\x07after", 20, 24);
+        assert_eq!(out.trim_end_matches('\n'), "beforeafter");
+    }
+
+    #[test]
+    fn osc_sequence_terminated_by_st_is_consumed_not_printed() {
+        let out = emulate("before\x1b]8;;http://example.com\x1b\\after", 40, 24);
+        assert_eq!(out.trim_end_matches('\n'), "beforeafter");
+    }
+
+    #[test]
+    fn single_byte_escape_is_consumed_not_printed() {
+        let out = emulate("before\x1b7after", 20, 24);
+        assert_eq!(out.trim_end_matches('\n'), "beforeafter");
+    }
+}
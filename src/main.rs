@@ -1,10 +1,22 @@
+mod capture;
+mod emulator;
+mod metadata;
+mod pty;
+mod template;
+mod timeout;
+
 use anyhow::{Context, Result};
+use capture::CaptureOutcome;
+use chrono::Utc;
 use clap::Parser;
+use metadata::RunRecord;
+use pty::{PtyChild, PtySize};
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
+use template::TemplateContext;
 
 #[derive(Parser, Debug)]
 #[command(name = "ansi-senor")]
@@ -18,6 +30,60 @@ struct Args {
     #[arg(short, long, default_value = "dark")]
     theme: Theme,
 
+    /// Run the command under a pseudo-terminal instead of plain pipes, so
+    /// tools that check `isatty()` emit real color and size their output
+    /// correctly (default on Unix)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pty: bool,
+
+    /// Terminal width to report to the child via the pty (defaults to the
+    /// current terminal's width, or 80)
+    #[arg(long)]
+    cols: Option<u16>,
+
+    /// Terminal height to report to the child via the pty (defaults to the
+    /// current terminal's height, or 24)
+    #[arg(long)]
+    rows: Option<u16>,
+
+    /// Write a JSON sidecar describing the run (program, args, dir, exit
+    /// code, duration, timestamps, env, output hash/path, tags) next to
+    /// the HTML output
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Tag to attach to the JSON sidecar; may be passed multiple times
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+
+    /// Kill the command if it runs longer than this (e.g. `30s`, `5m`).
+    /// The command is sent SIGTERM, then SIGKILL after a short grace
+    /// period, and whatever output was captured so far is still saved.
+    #[arg(long, value_parser = timeout::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Resolve cursor-control sequences (carriage returns, cursor moves,
+    /// line/screen erase) through an in-memory terminal grid before
+    /// converting to HTML, so progress bars and spinners show their final
+    /// frame instead of every overwritten redraw
+    #[arg(long)]
+    emulate: bool,
+
+    /// HTML template file with `{{title}}`, `{{background}}`,
+    /// `{{foreground}}`, `{{font}}`, `{{body}}`, `{{command}}`,
+    /// `{{duration}}`, and `{{exit_code}}` placeholders. Falls back to
+    /// the built-in template when omitted.
+    #[arg(long)]
+    template: Option<PathBuf>,
+
+    /// Override the HTML document's title (defaults to the command)
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Override the HTML document's font-family
+    #[arg(long)]
+    font: Option<String>,
+
     /// Command to run
     #[arg(required = true, trailing_var_arg = true)]
     command: Vec<String>,
@@ -66,9 +132,20 @@ fn main() -> Result<()> {
 
     // Start timing
     let start_time = Instant::now();
+    let start_timestamp = Utc::now();
 
     // Run the command and capture output
-    let (output_text, exit_status) = run_command_with_capture(&args.command)?;
+    let outcome = if args.pty {
+        let size = PtySize::detect_or(args.cols, args.rows);
+        run_command_with_pty(&args.command, size, args.timeout)?
+    } else {
+        capture::run(&args.command, args.timeout)?
+    };
+    let CaptureOutcome {
+        output_text,
+        exit_code: exit_status,
+        timed_out,
+    } = outcome;
 
     // Calculate elapsed time
     let elapsed = start_time.elapsed();
@@ -77,14 +154,35 @@ fn main() -> Result<()> {
     println!("\n---");
     print!("❯ {}", args.command.join(" "));
     println!("{}", format_duration(elapsed));
+    if timed_out {
+        println!(
+            "[ansi-senor] command killed after exceeding --timeout of {}",
+            humantime::format_duration(args.timeout.unwrap_or_default())
+        );
+    }
     print!("{}", output_text);
     if !output_text.ends_with('\n') {
         println!();
     }
     println!("---\n");
 
+    // Fold the timeout banner into the captured output itself, not just the
+    // live console, so the generated HTML/metadata also records that the
+    // command was killed rather than exiting on its own.
+    let mut output_text = output_text;
+    if timed_out {
+        if !output_text.ends_with('\n') {
+            output_text.push('\n');
+        }
+        output_text.push_str(&format!(
+            "[ansi-senor] command killed after exceeding --timeout of {}\n",
+            humantime::format_duration(args.timeout.unwrap_or_default())
+        ));
+    }
+
     // Generate output filename (hash based on output content)
-    let output_path = generate_output_path(&args.command, &output_text, args.output)?;
+    let output_hash = compute_output_hash(&output_text);
+    let output_path = generate_output_path(&args.command, &output_hash, args.output)?;
 
     // Ensure output directory exists
     if let Some(parent) = output_path.parent() {
@@ -93,38 +191,31 @@ fn main() -> Result<()> {
     }
 
     // Convert ANSI to HTML and save
+    let html_source = if args.emulate {
+        let size = PtySize::detect_or(args.cols, args.rows);
+        emulator::emulate(&output_text, size.cols as usize, size.rows as usize)
+    } else {
+        output_text.clone()
+    };
     let html_content =
-        ansi_to_html::convert(&output_text).context("Failed to convert ANSI to HTML")?;
-
-    let full_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>{}</title>
-    <style>
-        body {{
-            background-color: {};
-            color: {};
-            font-family: 'Consolas', 'Courier New', monospace;
-            padding: 20px;
-            margin: 0;
-        }}
-        pre {{
-            white-space: pre-wrap;
-            word-wrap: break-word;
-        }}
-    </style>
-</head>
-<body>
-    <pre>{}</pre>
-</body>
-</html>"#,
-        args.command.join(" "),
-        args.theme.background_color(),
-        args.theme.text_color(),
-        html_content
-    );
+        ansi_to_html::convert(&html_source).context("Failed to convert ANSI to HTML")?;
+
+    let command_str = args.command.join(" ");
+    let template_source = template::load(args.template.as_deref())?;
+    let context = TemplateContext {
+        title: args.title.clone().unwrap_or_else(|| command_str.clone()),
+        background: args.theme.background_color().to_string(),
+        foreground: args.theme.text_color().to_string(),
+        font: args
+            .font
+            .clone()
+            .unwrap_or_else(|| "'Consolas', 'Courier New', monospace".to_string()),
+        body: html_content,
+        command: command_str,
+        duration: format_duration(elapsed),
+        exit_code: exit_status.to_string(),
+    };
+    let full_html = context.render(&template_source);
 
     fs::write(&output_path, full_html).context(format!(
         "Failed to write output file: {}",
@@ -133,79 +224,88 @@ fn main() -> Result<()> {
 
     println!("Output saved to {}", output_path.display());
 
+    // Write the JSON metadata sidecar, if requested
+    if let Some(json_path) = args.json {
+        let dir = std::env::current_dir().context("Failed to get current directory")?;
+        let mut record = RunRecord::new(
+            &args.command,
+            dir,
+            exit_status,
+            elapsed,
+            start_timestamp,
+            output_hash,
+            output_path.clone(),
+            args.tags,
+        );
+        if timed_out {
+            record.mark_timed_out();
+        }
+        record.write_to(&json_path)?;
+        println!("Metadata saved to {}", json_path.display());
+    }
+
     // Exit with the same status as the command
     std::process::exit(exit_status);
 }
 
-fn run_command_with_capture(command: &[String]) -> Result<(String, i32)> {
+fn run_command_with_pty(
+    command: &[String],
+    size: pty::PtySize,
+    timeout_after: Option<Duration>,
+) -> Result<CaptureOutcome> {
     let program = &command[0];
     let args = &command[1..];
 
-    let mut child = Command::new(program)
-        .args(args)
-        .env("CLICOLOR_FORCE", "1")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .context(format!("Failed to execute command: {}", program))?;
-
-    let mut output_buffer = Vec::new();
-
-    // Capture stdout
-    if let Some(stdout) = child.stdout.take() {
-        let reader = BufReader::new(stdout);
-        for line in reader.split(b'\n') {
-            let line = line.context("Failed to read stdout")?;
-            // Print to console
-            std::io::stdout().write_all(&line)?;
-            if !line.is_empty() || output_buffer.last() != Some(&b'\n') {
-                std::io::stdout().write_all(b"\n")?;
-            }
-            std::io::stdout().flush()?;
-            // Save to buffer
-            output_buffer.extend_from_slice(&line);
-            output_buffer.push(b'\n');
-        }
-    }
-
-    // Capture stderr
-    if let Some(stderr) = child.stderr.take() {
-        let reader = BufReader::new(stderr);
-        for line in reader.split(b'\n') {
-            let line = line.context("Failed to read stderr")?;
-            // Print to console
-            std::io::stderr().write_all(&line)?;
-            if !line.is_empty() || output_buffer.last() != Some(&b'\n') {
-                std::io::stderr().write_all(b"\n")?;
-            }
-            std::io::stderr().flush()?;
-            // Save to buffer
-            output_buffer.extend_from_slice(&line);
-            output_buffer.push(b'\n');
-        }
+    let mut pty_child = PtyChild::spawn(program, args, size)?;
+
+    let timed_out = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    let watcher = timeout_after.map(|deadline| {
+        timeout::spawn_watcher(
+            pty_child.pid(),
+            deadline,
+            Arc::clone(&timed_out),
+            Arc::clone(&finished),
+        )
+    });
+
+    // read_to_end streams each chunk to stdout live as it arrives, so the
+    // buffer it returns is only for building the HTML/metadata output.
+    let output_buffer = pty_child.read_to_end()?;
+    finished.store(true, Ordering::SeqCst);
+    if let Some(watcher) = watcher {
+        let _ = watcher.join();
     }
 
-    let status = child.wait().context("Failed to wait for command")?;
+    let status = pty_child
+        .child
+        .wait()
+        .context("Failed to wait for command")?;
     let exit_code = status.code().unwrap_or(1);
 
     let output_text = String::from_utf8_lossy(&output_buffer).to_string();
 
-    Ok((output_text, exit_code))
+    Ok(CaptureOutcome {
+        output_text,
+        exit_code,
+        timed_out: timed_out.load(Ordering::SeqCst),
+    })
 }
 
-fn generate_output_path(command: &[String], output_text: &str, custom_output: Option<PathBuf>) -> Result<PathBuf> {
+fn compute_output_hash(output_text: &str) -> String {
+    let digest = md5::compute(output_text.as_bytes());
+    format!("{:x}", digest)
+}
+
+fn generate_output_path(command: &[String], output_hash: &str, custom_output: Option<PathBuf>) -> Result<PathBuf> {
     if let Some(path) = custom_output {
         return Ok(path);
     }
 
-    // Generate hash from output content
-    let digest = md5::compute(output_text.as_bytes());
-    let hash = format!("{:x}", digest);
-
     // Get the full command for the filename (replace spaces with dashes)
     let command_name = command.join(" ").replace(' ', "-");
 
-    let filename = format!("{}-{}.html", command_name, &hash[..8]);
+    let filename = format!("{}-{}.html", command_name, &output_hash[..8]);
 
     // Use system temp directory with ansi-senor subdirectory
     let temp_dir = std::env::temp_dir().join("ansi-senor");
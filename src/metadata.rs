@@ -0,0 +1,117 @@
+//! Structured provenance for a captured run, written out as a JSON sidecar
+//! next to the HTML so captures can be indexed and diffed programmatically
+//! instead of scraped back out of markup.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment variables worth recording alongside a run. Capturing the
+/// whole environment would leak secrets into the sidecar, so we only keep
+/// the handful that actually explain how the output looked.
+const RELEVANT_ENV_VARS: &[&str] = &["TERM", "COLORTERM", "SHELL", "LANG", "CLICOLOR_FORCE"];
+
+/// Whether the child process completed normally or was cut short.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Success,
+    Failure,
+    TimedOut,
+}
+
+/// Full provenance of one captured run: what was executed, where, how it
+/// went, and where its HTML landed. Built only when a JSON sidecar is
+/// written; the HTML output is rendered from a separate `TemplateContext`
+/// and does not read this record.
+#[derive(Debug, Serialize)]
+pub struct RunRecord {
+    pub program: String,
+    pub args: Vec<String>,
+    pub dir: PathBuf,
+    pub status: RunStatus,
+    pub exit_code: i32,
+    pub duration_ms: u128,
+    pub start: DateTime<Utc>,
+    pub env: BTreeMap<String, String>,
+    pub output_hash: String,
+    pub output_path: PathBuf,
+    pub tags: Vec<String>,
+}
+
+impl RunRecord {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        command: &[String],
+        dir: PathBuf,
+        exit_code: i32,
+        duration: std::time::Duration,
+        start: DateTime<Utc>,
+        output_hash: String,
+        output_path: PathBuf,
+        tags: Vec<String>,
+    ) -> Self {
+        let status = if exit_code == 0 {
+            RunStatus::Success
+        } else {
+            RunStatus::Failure
+        };
+
+        let env = RELEVANT_ENV_VARS
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.to_string(), value)))
+            .collect();
+
+        RunRecord {
+            program: resolve_program_path(&command[0]),
+            args: command[1..].to_vec(),
+            dir,
+            status,
+            exit_code,
+            duration_ms: duration.as_millis(),
+            start,
+            env,
+            output_hash,
+            output_path,
+            tags,
+        }
+    }
+
+    pub fn mark_timed_out(&mut self) {
+        self.status = RunStatus::TimedOut;
+    }
+
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize run record")?;
+        std::fs::write(path, json)
+            .context(format!("Failed to write JSON metadata: {}", path.display()))
+    }
+}
+
+/// Resolve `program` to the absolute path of the executable that would
+/// actually run, the way the shell would find it: as-is if it already
+/// contains a path separator, otherwise by searching `$PATH`. Falls back
+/// to the unresolved name if nothing on disk matches, so a `RunRecord`
+/// is still produced for commands resolved by other means (shell
+/// builtins, `exec`-style lookups we don't replicate here).
+fn resolve_program_path(program: &str) -> String {
+    let as_typed = Path::new(program);
+    if as_typed.components().count() > 1 {
+        return std::fs::canonicalize(as_typed)
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| program.to_string());
+    }
+
+    std::env::var_os("PATH")
+        .and_then(|path_var| {
+            std::env::split_paths(&path_var).find_map(|dir| {
+                let candidate = dir.join(program);
+                candidate.is_file().then_some(candidate)
+            })
+        })
+        .and_then(|candidate| std::fs::canonicalize(candidate).ok())
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| program.to_string())
+}
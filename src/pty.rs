@@ -0,0 +1,157 @@
+//! Pseudo-terminal support so captured commands see a real tty.
+//!
+//! Many CLIs only colorize output or size their layout when `isatty()`
+//! succeeds, so piping `Stdio::piped()` silently flattens their output.
+//! Allocating a PTY and running the child against the slave side gives us
+//! output byte-for-byte identical to what a user would see in their own
+//! terminal.
+
+use anyhow::{Context, Result};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::libc;
+use nix::pty::{openpty, Winsize};
+use nix::unistd::setsid;
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+
+/// Terminal dimensions used to size the PTY before spawning the child.
+#[derive(Debug, Clone, Copy)]
+pub struct PtySize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl PtySize {
+    /// Fall back to the attached terminal's size, or 80x24 if we aren't
+    /// attached to one (e.g. running under CI).
+    pub fn detect_or(cols: Option<u16>, rows: Option<u16>) -> Self {
+        let (detected_cols, detected_rows) = terminal_size::terminal_size()
+            .map(|(w, h)| (w.0, h.0))
+            .unwrap_or((80, 24));
+
+        PtySize {
+            cols: cols.unwrap_or(detected_cols),
+            rows: rows.unwrap_or(detected_rows),
+        }
+    }
+}
+
+/// A spawned child running against the slave end of a PTY, with the
+/// master end left open for the parent to read from.
+pub struct PtyChild {
+    pub child: Child,
+    master: OwnedFd,
+}
+
+impl PtyChild {
+    /// Pid of the spawned child, for external deadline enforcement.
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Spawn `program` with `args` attached to a freshly allocated PTY of
+    /// the given size.
+    pub fn spawn(program: &str, args: &[String], size: PtySize) -> Result<Self> {
+        let winsize = Winsize {
+            ws_row: size.rows,
+            ws_col: size.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+
+        let pty = openpty(Some(&winsize), None).context("Failed to open pty")?;
+        let master = pty.master;
+        let slave = pty.slave;
+        let slave_fd = slave.as_raw_fd();
+
+        // Make reads from the master non-blocking; `read_to_end` sleeps and
+        // retries on `WouldBlock` until EOF or `EIO`. The read loop itself
+        // checks no deadline: `--timeout` is enforced by a separate watcher
+        // thread that signals the child.
+        set_nonblocking(master.as_raw_fd())?;
+
+        let mut command = Command::new(program);
+        command.args(args).env("CLICOLOR_FORCE", "1");
+
+        unsafe {
+            command.pre_exec(move || {
+                // Detach from the parent's controlling terminal and become
+                // session leader so the slave fd can become our new
+                // controlling terminal.
+                setsid().map_err(std::io::Error::from)?;
+                if libc::ioctl(slave_fd, libc::TIOCSCTTY as _, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        // Each Stdio below takes ownership of the fd it wraps, so give
+        // stdin/stdout/stderr their own duplicate of the slave rather
+        // than aliasing the same fd three times.
+        command
+            .stdin(unsafe { Stdio::from_raw_fd(dup_fd(slave_fd)?) })
+            .stdout(unsafe { Stdio::from_raw_fd(dup_fd(slave_fd)?) })
+            .stderr(unsafe { Stdio::from_raw_fd(dup_fd(slave_fd)?) });
+
+        let child = command
+            .spawn()
+            .context(format!("Failed to execute command: {}", program))?;
+
+        // The child has its own copy of the slave fd now; drop ours so
+        // reads on the master return EOF once the child exits.
+        drop(slave);
+
+        Ok(PtyChild { child, master })
+    }
+
+    /// Read everything the child writes until it exits or the pipe closes,
+    /// echoing each chunk to stdout as it arrives so long-running or hung
+    /// commands still show live output instead of going silent until exit.
+    /// `EIO` is the normal signal that the slave side has gone away.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let mut file = unsafe {
+            std::fs::File::from_raw_fd(dup_fd(self.master.as_raw_fd())?)
+        };
+        let mut stdout = std::io::stdout();
+
+        loop {
+            match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    stdout.write_all(&chunk[..n])?;
+                    stdout.flush()?;
+                    buffer.extend_from_slice(&chunk[..n]);
+                }
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                    continue;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e).context("Failed to read from pty master"),
+            }
+        }
+
+        Ok(buffer)
+    }
+}
+
+fn set_nonblocking(fd: RawFd) -> Result<()> {
+    let flags = fcntl(fd, FcntlArg::F_GETFL).context("fcntl F_GETFL failed")?;
+    let flags = OFlag::from_bits_truncate(flags) | OFlag::O_NONBLOCK;
+    fcntl(fd, FcntlArg::F_SETFL(flags)).context("fcntl F_SETFL failed")?;
+    Ok(())
+}
+
+fn dup_fd(fd: RawFd) -> Result<RawFd> {
+    let new_fd = unsafe { libc::dup(fd) };
+    if new_fd < 0 {
+        return Err(std::io::Error::last_os_error()).context("dup failed");
+    }
+    Ok(new_fd)
+}
@@ -0,0 +1,84 @@
+//! HTML template rendering.
+//!
+//! Separates the presentation layer from the capture logic: callers fill
+//! in a `TemplateContext` with the captured run's details and render it
+//! against either a user-supplied `--template` file or the built-in
+//! document, substituting named `{{placeholder}}` tokens.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// The document used when no `--template` is supplied.
+pub const DEFAULT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>{{title}}</title>
+    <style>
+        body {
+            background-color: {{background}};
+            color: {{foreground}};
+            font-family: {{font}};
+            padding: 20px;
+            margin: 0;
+        }
+        pre {
+            white-space: pre-wrap;
+            word-wrap: break-word;
+        }
+    </style>
+</head>
+<body>
+    <pre>{{body}}</pre>
+</body>
+</html>"#;
+
+/// Values substituted into a template's `{{placeholder}}` tokens.
+pub struct TemplateContext {
+    pub title: String,
+    pub background: String,
+    pub foreground: String,
+    pub font: String,
+    pub body: String,
+    pub command: String,
+    pub duration: String,
+    pub exit_code: String,
+}
+
+impl TemplateContext {
+    /// Substitute every `{{placeholder}}` with its value. `{{body}}` is
+    /// substituted last: it's the captured command output, so it may
+    /// itself contain the literal text of another placeholder, and
+    /// substituting it first would let that text get rewritten.
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{{title}}", &escape_html(&self.title))
+            .replace("{{background}}", &self.background)
+            .replace("{{foreground}}", &self.foreground)
+            .replace("{{font}}", &escape_html(&self.font))
+            .replace("{{command}}", &escape_html(&self.command))
+            .replace("{{duration}}", &escape_html(&self.duration))
+            .replace("{{exit_code}}", &escape_html(&self.exit_code))
+            .replace("{{body}}", &self.body)
+    }
+}
+
+/// Escape the characters that matter inside HTML text/attribute context.
+/// `self.body` is deliberately not passed through this: it's already
+/// HTML produced by `ansi_to_html::convert`, not plain text.
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Read the template at `path`, or fall back to [`DEFAULT_TEMPLATE`] when
+/// none was given.
+pub fn load(path: Option<&Path>) -> Result<String> {
+    match path {
+        Some(path) => std::fs::read_to_string(path)
+            .context(format!("Failed to read template: {}", path.display())),
+        None => Ok(DEFAULT_TEMPLATE.to_string()),
+    }
+}
@@ -0,0 +1,51 @@
+//! Shared deadline enforcement for the capture backends: once `timeout`
+//! elapses without the run finishing, send `SIGTERM`, give the child a
+//! short grace period to exit cleanly, then `SIGKILL` it.
+
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const GRACE_PERIOD: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Spawn a thread that kills `pid` if `finished` hasn't been set by the
+/// time `timeout` elapses. Sets `timed_out` before delivering the signal
+/// so the caller can tell a killed run apart from one that exited on its
+/// own within the deadline.
+pub fn spawn_watcher(
+    pid: u32,
+    timeout: Duration,
+    timed_out: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if finished.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        if finished.load(Ordering::SeqCst) {
+            return;
+        }
+
+        timed_out.store(true, Ordering::SeqCst);
+        let target = Pid::from_raw(pid as i32);
+        let _ = signal::kill(target, Signal::SIGTERM);
+
+        thread::sleep(GRACE_PERIOD);
+        if !finished.load(Ordering::SeqCst) {
+            let _ = signal::kill(target, Signal::SIGKILL);
+        }
+    })
+}
+
+/// Parse durations like `30s` or `5m` for the `--timeout` flag.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    humantime::parse_duration(s).map_err(|e| format!("Invalid duration '{}': {}", s, e))
+}
@@ -0,0 +1,36 @@
+use std::process::Command;
+
+/// The `interleaver` fixture alternates lines between stdout and stderr
+/// with a delay between each. Capturing stdout to completion before
+/// starting on stderr (the old behavior) would group all `out-*` lines
+/// before all `err-*` lines; concurrent capture preserves arrival order.
+#[test]
+fn merges_stdout_and_stderr_in_arrival_order() {
+    let interleaver = env!("CARGO_BIN_EXE_interleaver");
+    let ansi_senor = env!("CARGO_BIN_EXE_ansi-senor");
+
+    let output = Command::new(ansi_senor)
+        .arg("--pty=false")
+        .arg(interleaver)
+        .output()
+        .expect("failed to run ansi-senor against the interleaver fixture");
+
+    let full_stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Real-time passthrough of stdout and stderr lands on separate
+    // pipes (stdout/stderr) and so can't show interleaving by itself;
+    // the merged capture is only visible in the buffered output block
+    // ansi-senor prints after the command header.
+    let header = full_stdout.find("❯ ").expect("missing command header");
+    let stdout = &full_stdout[header..];
+
+    let out_index = stdout.find("out-0").expect("missing out-0");
+    let err_index = stdout.find("err-1").expect("missing err-1");
+    let out_index_2 = stdout.find("out-2").expect("missing out-2");
+
+    assert!(
+        out_index < err_index && err_index < out_index_2,
+        "expected stdout/stderr lines interleaved in arrival order, got:\n{}",
+        stdout
+    );
+}